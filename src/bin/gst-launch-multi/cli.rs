@@ -1,6 +1,7 @@
 use std::collections::VecDeque;
 
 use clap::{Parser, Subcommand};
+use serde::Deserialize;
 
 const DEFAULT_PIPELINE_NAME: &str = "pipeline_";
 
@@ -10,6 +11,20 @@ pub(crate) struct CliArgs {
     pub(crate) pipeline_config: Vec<PipelineConfig>,
 }
 
+/// Loads the pipelines described in a declarative YAML or JSON config file, keyed by their
+/// unique `name`. The format is picked from the file extension (`.json`, or `.yaml`/`.yml`).
+pub(crate) fn load_pipeline_configs_from_file(path: &str) -> anyhow::Result<Vec<PipelineConfig>> {
+    let contents = std::fs::read_to_string(path)?;
+
+    let configs: Vec<PipelineConfig> = if path.ends_with(".json") {
+        serde_json::from_str(&contents)?
+    } else {
+        serde_yaml::from_str(&contents)?
+    };
+
+    Ok(configs)
+}
+
 impl CliArgs {
     pub(crate) fn parse() -> Result<Self, clap::Error> {
         let args = std::env::args().collect::<Vec<_>>();
@@ -44,6 +59,18 @@ impl CliArgs {
         let app_args = args_groups.pop_front().unwrap().to_owned();
         let mut app_config = AppConfig::parse_from(app_args);
 
+        // A declarative config file replaces the `--pipeline ...` arg-splitting scheme
+        // entirely: pipelines are described as data instead of as repeated CLI groups.
+        if let Some(config_path) = &app_config.config {
+            let pipeline_configs = load_pipeline_configs_from_file(config_path)
+                .map_err(|e| clap::Error::raw(clap::error::ErrorKind::Io, e.to_string()))?;
+
+            return Ok(CliArgs {
+                app_config,
+                pipeline_config: pipeline_configs,
+            });
+        }
+
         let mut pipeline_configs: Vec<PipelineConfig> = Vec::new();
 
         // if app_config
@@ -100,6 +127,53 @@ pub(crate) struct AppConfig {
     #[clap(long, required = false, default_value = "grpc://localhost:4317")]
     pub(crate) opentelemetry_url: String,
 
+    /// Address to listen on for remote control commands (e.g. "127.0.0.1:7878").
+    /// When set, a line-oriented TCP server accepts the same commands as the
+    /// interactive CLI and broadcasts them on the command channel.
+    #[clap(long, required = false)]
+    pub(crate) control_listen: Option<String>,
+
+    /// How long, in milliseconds, to wait after a graceful shutdown signal (SIGINT/SIGTERM)
+    /// for pipelines to drain their EOS before forcing them to stop.
+    #[clap(long, required = false, default_value_t = 5_000)]
+    pub(crate) stop_timeout_ms: u64,
+
+    /// Path to a YAML or JSON file declaring the pipelines to run, as an alternative to
+    /// `--pipeline` arguments. The file is watched for changes and reconciled live: added and
+    /// removed entries, and entries whose `spec` changed, are reflected without restarting the
+    /// process. Changes to an entry's other fields alone are not detected.
+    #[clap(long, required = false)]
+    pub(crate) config: Option<String>,
+
+    /// Path to a YAML or JSON file declaring bus-event rules: `{ match, action }` pairs that
+    /// fire a `SubCommand` (or spawn a shell command) whenever a bus message matches.
+    #[clap(long, required = false)]
+    pub(crate) rules: Option<String>,
+
+    /// Size, in milliseconds, of the quantum used to stagger pipeline admission (the
+    /// NULL->PLAYING transition), and, if `--throttled-runtime` is set, to batch the command
+    /// and bus task wakeups of every running pipeline.
+    #[clap(long, required = false, default_value_t = 20)]
+    pub(crate) throttle_ms: u64,
+
+    /// Run every pipeline's command and bus tasks on a dedicated runtime that coalesces
+    /// wakeups into quanta of `--throttle-ms` instead of polling immediately on every event.
+    /// Trades up to one quantum of added latency for far fewer context switches; left off by
+    /// default so low-latency users keep today's immediate behavior.
+    #[clap(long, required = false, default_value_t = false)]
+    pub(crate) throttled_runtime: bool,
+
+    /// Port to serve this process's clock on, so other `gst-launch-multi` instances can
+    /// synchronize against it via `--clock-client`.
+    #[clap(long, required = false)]
+    pub(crate) clock_provider: Option<u16>,
+
+    /// Address ("host:port") of a `gst-launch-multi` instance started with `--clock-provider`.
+    /// When set, every pipeline in this process synchronizes to that remote clock instead of
+    /// its own local system clock.
+    #[clap(long, required = false)]
+    pub(crate) clock_client: Option<String>,
+
     // Used to capture any other arguments. They are used to create
     // a PipelineConfig during the parsing process
     #[clap()]
@@ -112,7 +186,8 @@ pub(crate) struct Cli {
     pub(crate) sub_command: SubCommand,
 }
 
-#[derive(Subcommand, Clone, Debug)]
+#[derive(Subcommand, Deserialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
 pub(crate) enum SubCommand {
     AddPipeline(PipelineConfig),
     StopPipeline(StopPipelineCommand),
@@ -121,27 +196,110 @@ pub(crate) enum SubCommand {
     PushLatencyEvent(PushLatencyEventCommand),
     SetLatency(SetLatencyCommand),
     GetLatency(GetLatencyCommand),
+    GetRtpSync(GetRtpSyncCommand),
     Exit,
 }
 
-#[derive(Parser, Debug, Clone)]
+/// Restart policy applied by the supervisor when a pipeline task ends.
+#[derive(clap::ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum RestartPolicy {
+    /// Never restart the pipeline, regardless of how it ended.
+    #[default]
+    Never,
+    /// Restart the pipeline only if it ended with an error.
+    OnFailure,
+    /// Always restart the pipeline, even after a clean stop (e.g. EOS).
+    Always,
+}
+
+fn default_pipeline_name() -> String {
+    DEFAULT_PIPELINE_NAME.to_string()
+}
+
+fn default_restart_max() -> u32 {
+    5
+}
+
+fn default_restart_delay_ms() -> u64 {
+    500
+}
+
+fn default_restart_max_delay_ms() -> u64 {
+    30_000
+}
+
+fn default_restart_healthy_after_ms() -> u64 {
+    60_000
+}
+
+#[derive(Parser, Deserialize, Debug, Clone)]
 pub(crate) struct PipelineConfig {
     /// Pipeline name
     #[clap(long, required = false, default_value = DEFAULT_PIPELINE_NAME)]
+    #[serde(default = "default_pipeline_name")]
     pub(crate) name: String,
 
+    /// Restart policy applied when this pipeline's task ends.
+    #[clap(long, required = false, value_enum, default_value_t = RestartPolicy::Never)]
+    #[serde(default)]
+    pub(crate) restart: RestartPolicy,
+
+    /// Maximum number of consecutive restart attempts before giving up.
+    #[clap(long, required = false, default_value_t = 5)]
+    #[serde(default = "default_restart_max")]
+    pub(crate) restart_max: u32,
+
+    /// Base delay, in milliseconds, before the first restart attempt. Doubles after each
+    /// consecutive failed attempt, up to `restart_max_delay_ms`.
+    #[clap(long, required = false, default_value_t = 500)]
+    #[serde(default = "default_restart_delay_ms")]
+    pub(crate) restart_delay_ms: u64,
+
+    /// Upper bound, in milliseconds, on the exponential restart backoff.
+    #[clap(long, required = false, default_value_t = 30_000)]
+    #[serde(default = "default_restart_max_delay_ms")]
+    pub(crate) restart_max_delay_ms: u64,
+
+    /// How long a pipeline must run, in milliseconds, before it is considered healthy again,
+    /// resetting the restart attempt counter and backoff delay.
+    #[clap(long, required = false, default_value_t = 60_000)]
+    #[serde(default = "default_restart_healthy_after_ms")]
+    pub(crate) restart_healthy_after_ms: u64,
+
+    /// Configure this pipeline's rtpbin elements to carry the sender's absolute NTP clock time
+    /// in an `rtp-hdr-ext-ntp-64` RTP header extension, and enable `add-reference-timestamp-meta`
+    /// on its payloaders, so receivers can synchronize from the first packets instead of
+    /// waiting for periodic RTCP Sender Reports.
+    #[clap(long, required = false, default_value_t = false)]
+    #[serde(default)]
+    pub(crate) rapid_rtp_sync: bool,
+
+    /// Target pipeline latency, in milliseconds, applied at startup via a `gst::event::Latency`
+    /// so independently launched pipelines, sharing the same clock and base time, settle to
+    /// the same fixed latency and render synchronized output.
+    #[clap(long, required = false)]
+    #[serde(default)]
+    pub(crate) pipeline_latency_ms: Option<u64>,
+
+    /// Latency, in milliseconds, applied uniformly to every rtpbin/jitterbuffer element's
+    /// `latency` property at startup.
+    #[clap(long, required = false)]
+    #[serde(default)]
+    pub(crate) rtp_latency_ms: Option<u64>,
+
     /// Pipeline spec
     #[clap(required = true)]
     pub(crate) spec: Vec<String>,
 }
 
-#[derive(Parser, Debug, Clone)]
+#[derive(Parser, Deserialize, Debug, Clone)]
 pub(crate) struct StopPipelineCommand {
     #[clap(required = false)]
     pub(crate) pipelines: Vec<String>,
 }
 
-#[derive(Parser, Debug, Clone)]
+#[derive(Parser, Deserialize, Debug, Clone)]
 pub(crate) struct SetPropertyCommand {
     #[clap(long, required = true)]
     pub(crate) pipeline: String,
@@ -156,7 +314,7 @@ pub(crate) struct SetPropertyCommand {
     pub(crate) value: String,
 }
 
-#[derive(Parser, Debug, Clone)]
+#[derive(Parser, Deserialize, Debug, Clone)]
 pub(crate) struct SwitchPadCommand {
     #[clap(long, required = true)]
     pub(crate) pipeline: String,
@@ -168,13 +326,13 @@ pub(crate) struct SwitchPadCommand {
     pub(crate) pad: String,
 }
 
-#[derive(Parser, Debug, Clone)]
+#[derive(Parser, Deserialize, Debug, Clone)]
 pub(crate) struct PushLatencyEventCommand {
     #[clap(long, required = true)]
     pub(crate) pipeline: String,
 }
 
-#[derive(Parser, Debug, Clone)]
+#[derive(Parser, Deserialize, Debug, Clone)]
 pub(crate) struct SetLatencyCommand {
     #[clap(long, required = true)]
     pub(crate) pipeline: String,
@@ -186,7 +344,7 @@ pub(crate) struct SetLatencyCommand {
     pub(crate) latency_ms: u64,
 }
 
-#[derive(Parser, Debug, Clone)]
+#[derive(Parser, Deserialize, Debug, Clone)]
 pub(crate) struct GetLatencyCommand {
     #[clap(long, required = true)]
     pub(crate) pipeline: String,
@@ -194,3 +352,14 @@ pub(crate) struct GetLatencyCommand {
     #[clap(long, required = false)]
     pub(crate) element: Option<String>,
 }
+
+#[derive(Parser, Deserialize, Debug, Clone)]
+pub(crate) struct GetRtpSyncCommand {
+    #[clap(long, required = true)]
+    pub(crate) pipeline: String,
+
+    /// Name of a single payloader to report on. Reports every payloader observed so far if
+    /// left unset.
+    #[clap(long, required = false)]
+    pub(crate) element: Option<String>,
+}
@@ -0,0 +1,94 @@
+use crate::cli;
+
+use anyhow::Result;
+use clap::Parser;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+
+/// Runs a line-oriented TCP control server that lets an external process
+/// inject `SubCommand`s the same way the interactive CLI does, and read back
+/// the responses (e.g. `GetLatency` results) as they are produced.
+pub(crate) async fn control_task(
+    listen_addr: String,
+    shutdown_tx: broadcast::Sender<()>,
+    command_tx: broadcast::Sender<cli::SubCommand>,
+    response_tx: broadcast::Sender<String>,
+) -> Result<()> {
+    let listener = TcpListener::bind(&listen_addr).await?;
+    println!("Control server: listening on {listen_addr}");
+
+    loop {
+        let (socket, peer_addr) = listener.accept().await?;
+        let shutdown_tx = shutdown_tx.clone();
+        let command_tx = command_tx.clone();
+        let response_rx = response_tx.subscribe();
+
+        tokio::spawn(async move {
+            println!("Control server: client connected: {peer_addr}");
+            if let Err(e) = handle_connection(socket, shutdown_tx, command_tx, response_rx).await {
+                println!("Control server: connection {peer_addr} failed: {e}");
+            }
+            println!("Control server: client disconnected: {peer_addr}");
+        });
+    }
+}
+
+async fn handle_connection(
+    socket: TcpStream,
+    shutdown_tx: broadcast::Sender<()>,
+    command_tx: broadcast::Sender<cli::SubCommand>,
+    mut response_rx: broadcast::Receiver<String>,
+) -> Result<()> {
+    let (reader, mut writer) = socket.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    break;
+                };
+
+                // need to start the string with the "binary" name, same as cli_task
+                let mut command = "control ".to_string();
+                command.push_str(line.trim());
+
+                match cli::Cli::try_parse_from(command.split_whitespace()) {
+                    Ok(cli_command) => {
+                        let is_exit = matches!(cli_command.sub_command, cli::SubCommand::Exit);
+
+                        match command_tx.send(cli_command.sub_command) {
+                            Ok(_) => writer.write_all(b"OK\n").await?,
+                            Err(e) => {
+                                writer
+                                    .write_all(format!("ERROR: failed to send command: {e}\n").as_bytes())
+                                    .await?;
+                                let _ = shutdown_tx.send(());
+                                break;
+                            }
+                        }
+
+                        if is_exit {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        writer
+                            .write_all(format!("ERROR: invalid command: {e}\n").as_bytes())
+                            .await?;
+                    }
+                }
+            }
+            response = response_rx.recv() => {
+                match response {
+                    Ok(response) => writer.write_all(format!("{response}\n").as_bytes()).await?,
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
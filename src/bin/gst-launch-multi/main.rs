@@ -1,12 +1,15 @@
 mod cli;
+mod control;
+mod netclock;
 mod pipeline;
+mod rules;
+mod throttle;
 
 use anyhow::Result;
 use clap::Parser;
 use gst::glib;
 use gst::prelude::ClockExt;
 
-use pipeline::run_pipeline;
 use tokio::sync::{broadcast, OnceCell};
 
 // It is important that all pipelines share both the same clock and basetime.
@@ -19,19 +22,96 @@ async fn main() -> Result<()> {
 
     let cli_args = cli::CliArgs::parse()?;
 
-    // create shutdown channel
+    // create shutdown channel. A message here starts a graceful drain: each pipeline is sent
+    // an EOS event and is expected to stop once it has been fully processed.
     let (shutdown_tx, shutdown_rx) = broadcast::channel::<()>(1);
 
+    // create force-stop channel. A message here stops every pipeline immediately (State::Null),
+    // without waiting for EOS to drain. It is used once the stop-timeout elapses, or on a
+    // second shutdown signal.
+    let (force_stop_tx, force_stop_rx) = broadcast::channel::<()>(1);
+
     // The command channel is used by the CLI task to broadcast commands to each pipelines
     // and the pipeline_controller_task
     let (command_tx, command_rx) = broadcast::channel::<cli::SubCommand>(1);
 
+    // The response channel carries command results (e.g. GetLatency output) back out of
+    // the pipelines, so remote clients connected through the control server can read them.
+    let (response_tx, _response_rx) = broadcast::channel::<String>(16);
+
     // There are two main tasks:
     //- The pipeline_controller_task is responsible for holding all the pipelines tasks.
     //- The cli_task is responsible for handling the CLI commands once the application starts.
     let mut task_set = tokio::task::JoinSet::new();
-    task_set.spawn_blocking(move || pipeline_controller_task(cli_args, shutdown_rx, command_rx));
-    task_set.spawn_blocking(move || cli_task(shutdown_tx, command_tx));
+
+    let control_listen = cli_args.app_config.control_listen.clone();
+    let stop_timeout_ms = cli_args.app_config.stop_timeout_ms;
+
+    // Bus-event rules are parsed once at startup and shared, read-only, by every pipeline.
+    let rules = std::sync::Arc::new(match &cli_args.app_config.rules {
+        Some(path) => rules::load_rules_from_file(path)?,
+        None => Vec::new(),
+    });
+
+    let throttle = throttle::Throttle::new(cli_args.app_config.throttle_ms);
+
+    let throttled_runtime = cli_args.app_config.throttled_runtime.then(|| {
+        throttle::ThrottledRuntime::spawn_thread(std::time::Duration::from_millis(
+            cli_args.app_config.throttle_ms,
+        ))
+    });
+
+    let clock_config = netclock::ClockConfig {
+        provider_port: cli_args.app_config.clock_provider,
+        client_addr: cli_args.app_config.clock_client.clone(),
+    };
+
+    let response_tx_clone = response_tx.clone();
+    let command_tx_clone = command_tx.clone();
+    task_set.spawn_blocking(move || {
+        pipeline_controller_task(
+            cli_args,
+            shutdown_rx,
+            command_rx,
+            command_tx_clone,
+            response_tx_clone,
+            force_stop_rx,
+            rules,
+            throttle,
+            throttled_runtime,
+            clock_config,
+        )
+    });
+
+    // Listen for SIGINT/SIGTERM and turn the first one into a graceful EOS-drain shutdown,
+    // forcing a hard stop if pipelines haven't drained within `stop_timeout_ms`, or immediately
+    // on a second signal.
+    task_set.spawn(signal_task(
+        shutdown_tx.clone(),
+        force_stop_tx,
+        stop_timeout_ms,
+    ));
+    task_set.spawn_blocking({
+        let shutdown_tx = shutdown_tx.clone();
+        let command_tx = command_tx.clone();
+        move || cli_task(shutdown_tx, command_tx)
+    });
+
+    // Optionally, expose the command channel over a TCP control server so an external
+    // orchestrator can drive the application instead of (or alongside) the interactive CLI.
+    if let Some(listen_addr) = control_listen {
+        task_set.spawn(control::control_task(
+            listen_addr,
+            shutdown_tx,
+            command_tx,
+            response_tx,
+        ));
+    } else {
+        // Nothing else will consume this sender: drop it explicitly so it doesn't linger for
+        // the rest of `main`. Other clones (the controller's, the CLI's, each pipeline's)
+        // still keep the broadcast channel open.
+        drop(command_tx);
+    }
 
     // wait for all the tasks to complete
     while task_set.join_next().await.is_some() {}
@@ -39,10 +119,130 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Waits for a shutdown signal (Ctrl-C or, on Unix, SIGTERM).
+async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = sigterm.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// On the first shutdown signal, broadcasts on `shutdown_tx` to begin a graceful EOS drain.
+/// If a second signal arrives, or `stop_timeout_ms` elapses first, broadcasts on
+/// `force_stop_tx` to stop every pipeline immediately.
+async fn signal_task(
+    shutdown_tx: broadcast::Sender<()>,
+    force_stop_tx: broadcast::Sender<()>,
+    stop_timeout_ms: u64,
+) -> Result<()> {
+    wait_for_shutdown_signal().await;
+    println!("Received shutdown signal, draining pipelines...");
+    let _ = shutdown_tx.send(());
+
+    tokio::select! {
+        _ = wait_for_shutdown_signal() => {
+            println!("Received second shutdown signal, forcing immediate stop");
+        }
+        _ = tokio::time::sleep(std::time::Duration::from_millis(stop_timeout_ms)) => {
+            println!("Stop timeout elapsed, forcing immediate stop");
+        }
+    }
+    let _ = force_stop_tx.send(());
+
+    Ok(())
+}
+
+/// Awaits the next tick of `interval` if it is `Some`, otherwise never resolves. Lets a
+/// `tokio::select!` branch on an optional periodic timer.
+async fn tick_if_some(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Diffs the pipelines declared in `new_configs` against `desired` (the pipelines reconciled
+/// from the config file on the previous tick, plus any added at runtime via `AddPipeline`),
+/// keyed by name: new names are started, names no
+/// longer present are stopped, and names whose spec changed are stopped then restarted.
+///
+/// Only names in `file_managed` are eligible for the removed-from-file stop, so a pipeline added
+/// at runtime (via `AddPipeline` from the CLI, control server, or a rule) and never declared in
+/// the file is left alone instead of being stopped as soon as the next reconciliation tick sees
+/// the file doesn't mention it. `file_managed` is kept in lockstep with the names this function
+/// itself adds to/removes from `desired`.
+///
+/// Note that only `spec` is diffed: changing a pipeline's `restart`/latency fields in the file
+/// without changing its `spec` is not detected and is silently ignored until the pipeline is
+/// otherwise restarted.
+fn reconcile(
+    desired: &mut std::collections::HashMap<String, cli::PipelineConfig>,
+    file_managed: &mut std::collections::HashSet<String>,
+    new_configs: Vec<cli::PipelineConfig>,
+    command_tx: &broadcast::Sender<cli::SubCommand>,
+) {
+    let new_by_name: std::collections::HashMap<String, cli::PipelineConfig> = new_configs
+        .into_iter()
+        .map(|config| (config.name.clone(), config))
+        .collect();
+
+    for name in file_managed.iter().cloned().collect::<Vec<_>>() {
+        if !new_by_name.contains_key(&name) {
+            println!("Config reconciliation: pipeline {name} removed from config, stopping it");
+            let _ = command_tx.send(cli::SubCommand::StopPipeline(cli::StopPipelineCommand {
+                pipelines: vec![name.clone()],
+            }));
+            desired.remove(&name);
+            file_managed.remove(&name);
+        }
+    }
+
+    for (name, config) in new_by_name {
+        match desired.get(&name) {
+            Some(existing) if existing.spec == config.spec => {
+                file_managed.insert(name);
+            }
+            Some(_) => {
+                println!("Config reconciliation: pipeline {name} spec changed, restarting it");
+                let _ = command_tx.send(cli::SubCommand::StopPipeline(cli::StopPipelineCommand {
+                    pipelines: vec![name.clone()],
+                }));
+                let _ = command_tx.send(cli::SubCommand::AddPipeline(config.clone()));
+                file_managed.insert(name.clone());
+                desired.insert(name, config);
+            }
+            None => {
+                println!("Config reconciliation: pipeline {name} added to config, starting it");
+                let _ = command_tx.send(cli::SubCommand::AddPipeline(config.clone()));
+                file_managed.insert(name.clone());
+                desired.insert(name, config);
+            }
+        }
+    }
+}
+
 fn pipeline_controller_task(
     cli_args: cli::CliArgs,
     shutdown_rx: broadcast::Receiver<()>,
     mut command_rx: broadcast::Receiver<cli::SubCommand>,
+    command_tx: broadcast::Sender<cli::SubCommand>,
+    response_tx: broadcast::Sender<String>,
+    force_stop_rx: broadcast::Receiver<()>,
+    rules: std::sync::Arc<Vec<rules::Rule>>,
+    throttle: throttle::Throttle,
+    throttled_runtime: Option<std::sync::Arc<throttle::ThrottledRuntime>>,
+    clock_config: netclock::ClockConfig,
 ) -> Result<()> {
     // as single GLib MainLoop for all pipelines
     let main_loop = glib::MainLoop::new(None, false);
@@ -50,6 +250,27 @@ fn pipeline_controller_task(
     // Stores all the tasks handlers for running pipelines.
     let mut pipeline_handlers = tokio::task::JoinSet::new();
 
+    // The pipelines started so far, by name: those from the command line or the declarative
+    // config file, plus any added via `AddPipeline`. Diffed against the config file's contents
+    // on every reconciliation tick.
+    let mut desired: std::collections::HashMap<String, cli::PipelineConfig> = cli_args
+        .pipeline_config
+        .iter()
+        .map(|config| (config.name.clone(), config.clone()))
+        .collect();
+    let config_path = cli_args.app_config.config.clone();
+
+    // Names `reconcile` actually owns, i.e. declared in the config file as of the last tick.
+    // `cli_args.pipeline_config` is loaded entirely from the file when one is given (see
+    // `cli::CliArgs::parse`), so every name `desired` starts with here is file-managed; a
+    // pipeline added later via `AddPipeline` only ever goes into `desired`, never into this set,
+    // so reconciliation never mistakes it for a name the file stopped declaring.
+    let mut file_managed: std::collections::HashSet<String> = if config_path.is_some() {
+        desired.keys().cloned().collect()
+    } else {
+        std::collections::HashSet::new()
+    };
+
     // create a new task for handling CLI commands
     // and stopping GLib main loop when all pipelines are finished
     let main_loop_clone = main_loop.clone();
@@ -62,28 +283,112 @@ fn pipeline_controller_task(
             for pipeline_config in cli_args.pipeline_config {
                 let rx = shutdown_rx.resubscribe();
                 let cmd_rx = command_rx.resubscribe();
+                let cmd_tx = command_tx.clone();
+                let resp_tx = response_tx.clone();
+                let force_rx = force_stop_rx.resubscribe();
+                let rules = rules.clone();
+                let clock_config = clock_config.clone();
 
-                pipeline_handlers.spawn_blocking(move || {
-                    let _ = create_and_run_pipeline(pipeline_config.clone(), rx, cmd_rx);
-                });
-
-                // NOTE: this sleep is needed to give pipelines time to start, removing it
-                // causes some segmentation fault.
-                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                // Staggers the NULL->PLAYING transition of each pipeline across a quantum
+                // boundary instead of a single fixed sleep, so a large pipeline count doesn't
+                // starve the runtime with dozens of simultaneous state-change admissions.
+                throttle.admit().await;
+                let pipeline_future = create_and_run_pipeline(
+                    pipeline_config.clone(),
+                    rx,
+                    cmd_rx,
+                    cmd_tx,
+                    resp_tx,
+                    force_rx,
+                    rules,
+                    clock_config,
+                    throttled_runtime.clone(),
+                );
+                match &throttled_runtime {
+                    Some(rt) => {
+                        let done_rx = rt.spawn(pipeline_future);
+                        pipeline_handlers.spawn(async move {
+                            let _ = done_rx.await;
+                        });
+                    }
+                    None => {
+                        pipeline_handlers.spawn(pipeline_future);
+                    }
+                }
             }
 
-            // Handle CLI commands
-            while let Ok(command) = command_rx.recv().await {
-                // this task only handles the AddPipeline command, all other commands
-                // are handle internally in each pipeline task
-                if let cli::SubCommand::AddPipeline(args) = command {
-                    let pipeline_shutdown_rx = shutdown_rx.resubscribe();
-                    let cmd_rx = command_rx.resubscribe();
-
-                    let args_clone = args.clone();
-                    pipeline_handlers.spawn_blocking(move || {
-                        let _ = create_and_run_pipeline(args_clone, pipeline_shutdown_rx, cmd_rx);
-                    });
+            // Poll the config file for changes every few seconds, if one was given.
+            let mut reconcile_interval = config_path
+                .as_ref()
+                .map(|_| tokio::time::interval(std::time::Duration::from_secs(3)));
+
+            // This task itself holds a `command_tx` clone (used above and below to drive
+            // reconciliation/`AddPipeline`), so the command channel never closes on its own:
+            // `command_rx.recv()` returning `Err` can't be relied on to end this loop. Subscribe
+            // to shutdown directly instead, and also break on `Exit`, so the loop has an explicit
+            // termination condition and always reaches the pipeline drain below.
+            let mut controller_shutdown_rx = shutdown_rx.resubscribe();
+
+            // Handle CLI commands, and reconcile the declarative config file if one is set
+            loop {
+                tokio::select! {
+                    _ = controller_shutdown_rx.recv() => {
+                        break;
+                    }
+                    command = command_rx.recv() => {
+                        let Ok(command) = command else {
+                            break;
+                        };
+
+                        if let cli::SubCommand::Exit = command {
+                            break;
+                        }
+
+                        // this task only handles the AddPipeline command, all other commands
+                        // are handle internally in each pipeline task
+                        if let cli::SubCommand::AddPipeline(args) = command {
+                            let pipeline_shutdown_rx = shutdown_rx.resubscribe();
+                            let cmd_rx = command_rx.resubscribe();
+                            let cmd_tx = command_tx.clone();
+                            let resp_tx = response_tx.clone();
+                            let force_rx = force_stop_rx.resubscribe();
+                            let rules = rules.clone();
+                            let clock_config = clock_config.clone();
+
+                            desired.insert(args.name.clone(), args.clone());
+                            throttle.admit().await;
+                            let pipeline_future = create_and_run_pipeline(
+                                args,
+                                pipeline_shutdown_rx,
+                                cmd_rx,
+                                cmd_tx,
+                                resp_tx,
+                                force_rx,
+                                rules,
+                                clock_config,
+                                throttled_runtime.clone(),
+                            );
+                            match &throttled_runtime {
+                                Some(rt) => {
+                                    let done_rx = rt.spawn(pipeline_future);
+                                    pipeline_handlers.spawn(async move {
+                                        let _ = done_rx.await;
+                                    });
+                                }
+                                None => {
+                                    pipeline_handlers.spawn(pipeline_future);
+                                }
+                            }
+                        }
+                    }
+                    _ = tick_if_some(&mut reconcile_interval) => {
+                        if let Some(path) = &config_path {
+                            match cli::load_pipeline_configs_from_file(path) {
+                                Ok(new_configs) => reconcile(&mut desired, &mut file_managed, new_configs, &command_tx),
+                                Err(e) => println!("Config reconciliation: failed to read {path}: {e}"),
+                            }
+                        }
+                    }
                 }
             }
 
@@ -101,36 +406,137 @@ fn pipeline_controller_task(
     Ok(())
 }
 
-fn create_and_run_pipeline(
+async fn create_and_run_pipeline(
     config: cli::PipelineConfig,
     shutdown_rx: broadcast::Receiver<()>,
     command_rx: broadcast::Receiver<cli::SubCommand>,
-) -> Result<()> {
-    tokio::runtime::Handle::current().block_on(async move {
+    command_tx: broadcast::Sender<cli::SubCommand>,
+    response_tx: broadcast::Sender<String>,
+    force_stop_rx: broadcast::Receiver<()>,
+    rules: std::sync::Arc<Vec<rules::Rule>>,
+    clock_config: netclock::ClockConfig,
+    throttled_runtime: Option<std::sync::Arc<throttle::ThrottledRuntime>>,
+) {
+    supervise_pipeline(
+        config,
+        shutdown_rx,
+        command_rx,
+        command_tx,
+        response_tx,
+        force_stop_rx,
+        rules,
+        clock_config,
+        throttled_runtime,
+    )
+    .await
+}
+
+/// Creates and runs a pipeline, applying the restart policy configured in `config.restart`
+/// whenever the pipeline task ends. Restart attempts use an exponential backoff starting at
+/// `restart_delay_ms`, capped at `restart_max_delay_ms`, and give up once `restart_max`
+/// consecutive attempts have been made. The backoff resets once a pipeline has run for at
+/// least `restart_healthy_after_ms` without ending.
+async fn supervise_pipeline(
+    config: cli::PipelineConfig,
+    shutdown_rx: broadcast::Receiver<()>,
+    command_rx: broadcast::Receiver<cli::SubCommand>,
+    command_tx: broadcast::Sender<cli::SubCommand>,
+    response_tx: broadcast::Sender<String>,
+    force_stop_rx: broadcast::Receiver<()>,
+    rules: std::sync::Arc<Vec<rules::Rule>>,
+    clock_config: netclock::ClockConfig,
+    throttled_runtime: Option<std::sync::Arc<throttle::ThrottledRuntime>>,
+) {
+    let mut attempt: u32 = 0;
+    let mut delay_ms = config.restart_delay_ms;
+
+    loop {
         // Gets a reference of the global clock and basetime. This pattern guarantees that the
         // basetime, in particular, is obtained only once during the lifetime of the application.
-        // Every pipeline created by the application must share the same clock and basetime.
-        let (clock, basetime) = GST_CLOCK_AND_BASETIME
-            .get_or_init(|| async {
-                let clock = gst::SystemClock::obtain();
-                let basetime = clock.time().unwrap();
-                (clock, basetime)
-            })
-            .await;
+        // Every pipeline created by the application must share the same clock and basetime,
+        // whether that's the local system clock or a shared clock resolved from
+        // `--clock-provider`/`--clock-client`.
+        let (clock, basetime) = match GST_CLOCK_AND_BASETIME
+            .get_or_try_init(|| netclock::resolve_clock(&clock_config))
+            .await
+        {
+            Ok(value) => value,
+            Err(err) => {
+                println!("Pipeline: {}: failed to resolve clock: {}", config.name, err);
+                return;
+            }
+        };
 
-        let pipeline = pipeline::Pipeline::new(&config, Some(clock), basetime).unwrap();
+        let pipeline = match pipeline::Pipeline::new(
+            &config,
+            Some(clock),
+            basetime,
+            command_tx.clone(),
+            response_tx.clone(),
+            rules.clone(),
+            throttled_runtime.clone(),
+        ) {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                println!("Pipeline: {}: failed to create: {}", config.name, err);
+                return;
+            }
+        };
         let pipeline_name = pipeline.config.name.clone();
 
-        println!("Pipeline: {}: starting", pipeline_name);
-        match pipeline.run(shutdown_rx, command_rx).await {
-            Ok(_) => {}
+        println!(
+            "Pipeline: {}: starting (attempt {})",
+            pipeline_name, attempt
+        );
+        let started_at = tokio::time::Instant::now();
+        let run_result = pipeline
+            .run(
+                shutdown_rx.resubscribe(),
+                command_rx.resubscribe(),
+                force_stop_rx.resubscribe(),
+            )
+            .await;
+
+        let stopped_by_command = pipeline.shared_settings.lock().unwrap().cli_stopped;
+
+        let should_restart = match &run_result {
+            Ok(_) => {
+                println!("Pipeline: {}: stopped", pipeline_name);
+                config.restart == cli::RestartPolicy::Always
+            }
             Err(err) => {
                 println!("Pipeline: {}: task failed: {}", pipeline_name, err);
+                config.restart != cli::RestartPolicy::Never
             }
+        };
+
+        if stopped_by_command || !should_restart {
+            return;
         }
-    });
 
-    Ok(())
+        // a sufficiently long healthy run resets the backoff counter
+        if started_at.elapsed() >= std::time::Duration::from_millis(config.restart_healthy_after_ms)
+        {
+            attempt = 0;
+            delay_ms = config.restart_delay_ms;
+        }
+
+        if attempt >= config.restart_max {
+            println!(
+                "Pipeline: {}: giving up after {} restart attempts",
+                pipeline_name, attempt
+            );
+            return;
+        }
+
+        attempt += 1;
+        println!(
+            "Pipeline: {}: restarting in {}ms (attempt {}/{})",
+            pipeline_name, delay_ms, attempt, config.restart_max
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+        delay_ms = (delay_ms * 2).min(config.restart_max_delay_ms);
+    }
 }
 
 fn cli_task(
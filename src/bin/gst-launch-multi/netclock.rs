@@ -0,0 +1,130 @@
+use anyhow::{anyhow, Result};
+use gst::prelude::{Cast, ClockExt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::OnceCell;
+
+/// Keeps the `NetTimeProvider` alive for the lifetime of the process once `--clock-provider`
+/// is used: dropping it would stop serving clock requests to connected clients.
+static NET_TIME_PROVIDER: OnceCell<gst_net::NetTimeProvider> = OnceCell::const_new();
+
+/// Which clock every pipeline in this process should share, resolved once at startup from
+/// `--clock-provider`/`--clock-client`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ClockConfig {
+    /// Port to serve this process's clock on, wrapping it in a `NetTimeProvider` so other
+    /// `gst-launch-multi` instances (or hosts) can synchronize against it via `--clock-client`.
+    pub(crate) provider_port: Option<u16>,
+
+    /// Address ("host:port") of a remote clock provider to synchronize against, instead of
+    /// this process's own local system clock.
+    pub(crate) client_addr: Option<String>,
+}
+
+/// Resolves the `gst::Clock` and base time every pipeline in this process must share, per
+/// `config`. With `--clock-client`, pipelines synchronize to a remote `NetClientClock` instead
+/// of the local system clock, so their timestamps line up with pipelines running in other
+/// processes or on other hosts, exactly as a common clock keeps multiple streams aligned in a
+/// single WebRTC precise-sync session. With `--clock-provider` (and no `--clock-client`), the
+/// local system clock is also served over the network via a `NetTimeProvider` so those remote
+/// processes have something to connect to; when both are set, `--clock-provider` is ignored.
+///
+/// `NetClientClock` only synchronizes the clock's *rate*; the base time is a separate, fixed
+/// epoch every process subtracts from its running time, and it is never part of that
+/// synchronization. Sampling it locally on each process (as `clock.time()` right after sync)
+/// would pick a different instant per process and defeat cross-process alignment, so the
+/// provider instead publishes its base time over a small side channel on `provider_port + 1`,
+/// and the client fetches that exact value instead of re-sampling its own clock.
+pub(crate) async fn resolve_clock(config: &ClockConfig) -> Result<(gst::Clock, gst::ClockTime)> {
+    if let Some(addr) = &config.client_addr {
+        let (host, port) = addr
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("--clock-client must be in the form host:port, got {addr:?}"))?;
+        let port: i32 = port
+            .parse()
+            .map_err(|e| anyhow!("invalid port in --clock-client {addr:?}: {e}"))?;
+
+        let client_clock =
+            gst_net::NetClientClock::new(Some("net_client_clock"), host, port, gst::ClockTime::ZERO);
+
+        client_clock
+            .wait_for_sync(gst::ClockTime::from_seconds(5))
+            .map_err(|e| anyhow!("timed out waiting for clock sync with {addr}: {e}"))?;
+
+        let clock = client_clock.upcast::<gst::Clock>();
+
+        let basetime_port = u16::try_from(port)
+            .map_err(|e| anyhow!("invalid port in --clock-client {addr:?}: {e}"))?
+            .checked_add(1)
+            .ok_or_else(|| anyhow!("--clock-client port {port} leaves no room for the base-time side channel on port + 1"))?;
+        let basetime = fetch_basetime(host, basetime_port).await?;
+
+        return Ok((clock, basetime));
+    }
+
+    let clock = gst::SystemClock::obtain();
+    let basetime = clock
+        .time()
+        .ok_or_else(|| anyhow!("clock did not report a time"))?;
+
+    if let Some(port) = config.provider_port {
+        let provider = gst_net::NetTimeProvider::new(&clock, None, i32::from(port))
+            .map_err(|e| anyhow!("failed to start clock provider on port {port}: {e}"))?;
+        // Only ever set once: every pipeline in the process shares the same provider instance.
+        let _ = NET_TIME_PROVIDER.set(provider);
+
+        // `basetime` above is the one instant every client must adopt verbatim; serve it on
+        // port + 1 rather than letting clients re-derive their own.
+        serve_basetime(port + 1, basetime).await?;
+    }
+
+    Ok((clock, basetime))
+}
+
+/// Binds the base-time side channel and spawns a background task that answers every connection
+/// with `basetime` (as nanoseconds), so `--clock-client` processes adopt this exact epoch instead
+/// of sampling their own.
+async fn serve_basetime(port: u16, basetime: gst::ClockTime) -> Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .await
+        .map_err(|e| anyhow!("failed to bind base-time side channel on port {port}: {e}"))?;
+
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    println!("clock provider: base-time side channel accept error: {e}");
+                    continue;
+                }
+            };
+
+            let _ = stream
+                .write_all(format!("{}\n", basetime.nseconds()).as_bytes())
+                .await;
+        }
+    });
+
+    Ok(())
+}
+
+/// Connects to a provider's base-time side channel and reads back the exact base time it is
+/// publishing.
+async fn fetch_basetime(host: &str, port: u16) -> Result<gst::ClockTime> {
+    let mut stream = TcpStream::connect((host, port))
+        .await
+        .map_err(|e| anyhow!("failed to connect to base-time side channel at {host}:{port}: {e}"))?;
+
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .await
+        .map_err(|e| anyhow!("failed to read base time from {host}:{port}: {e}"))?;
+
+    let nanos: u64 = response
+        .trim()
+        .parse()
+        .map_err(|e| anyhow!("invalid base time {response:?} received from {host}:{port}: {e}"))?;
+
+    Ok(gst::ClockTime::from_nseconds(nanos))
+}
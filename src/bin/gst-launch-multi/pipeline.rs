@@ -1,8 +1,10 @@
+use std::future::Future;
 use std::sync::Arc;
 
 use crate::cli;
 
 use anyhow::Result;
+use futures::StreamExt;
 use gst::prelude::{
     Cast, ElementExt, ElementExtManual, GObjectExtManualGst, GstBinExt, GstBinExtManual,
     GstObjectExt, ObjectExt, PadExtManual,
@@ -29,6 +31,155 @@ pub(crate) struct Pipeline {
     /// in a Tokio task may lead to blocking the whole Tokio runtime running several tasks.
     /// However, this mutex is needed inside a GStreamer PadProbe function.
     pub(crate) shared_settings: std::sync::Arc<std::sync::Mutex<PipelineSharedSettings>>,
+
+    /// Carries command results (e.g. `GetLatency` output) out of the pipeline, so clients
+    /// connected through the control server can read them back.
+    pub(crate) response_tx: broadcast::Sender<String>,
+
+    /// Broadcasts `SubCommand`s, used by the bus-event rules engine to fire actions.
+    pub(crate) command_tx: broadcast::Sender<cli::SubCommand>,
+
+    /// Bus-event rules, parsed once at startup and shared read-only across every pipeline.
+    pub(crate) rules: std::sync::Arc<Vec<crate::rules::Rule>>,
+
+    /// The most recent reference timestamp (the sender's absolute NTP clock time, carried in
+    /// the `rtp-hdr-ext-ntp-64` header extension) observed on each payloader's src pad, keyed
+    /// by element name. Only populated when `config.rapid_rtp_sync` is set.
+    pub(crate) rtp_sync_timestamps:
+        std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, gst::ClockTime>>>,
+
+    /// When `--throttled-runtime` is set, the command and bus tasks spawned in `run` are driven
+    /// through this runtime too, so their wakeups are coalesced the same way the top-level
+    /// pipeline future is in `main.rs`. `None` keeps them on the default per-wakeup scheduling.
+    pub(crate) throttled_runtime: Option<std::sync::Arc<crate::throttle::ThrottledRuntime>>,
+}
+
+/// Configures every `rtpbin` element in `pipeline` to carry the sender's absolute NTP clock
+/// time in an `rtp-hdr-ext-ntp-64` RTP header extension, and enables
+/// `add-reference-timestamp-meta` on its payloaders, so a receiver can synchronize instantly
+/// from the first packets instead of waiting for periodic RTCP Sender Reports. Each payloader's
+/// src pad gets a probe that records the reference timestamp of the last buffer it saw into
+/// `rtp_sync_timestamps`, keyed by element name, so it can be read back via `GetRtpSync`.
+fn configure_rapid_rtp_sync(
+    pipeline: &gst::Pipeline,
+    rtp_sync_timestamps: &std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, gst::ClockTime>>>,
+) -> Result<()> {
+    for rtpbin in pipeline
+        .iterate_all_by_element_factory_name("rtpbin")
+        .into_iter()
+        .flatten()
+    {
+        if rtpbin.has_property("ntp-sync", None) {
+            rtpbin.set_property("ntp-sync", true);
+        }
+        if rtpbin.has_property("rtcp-sync-send-time", None) {
+            rtpbin.set_property("rtcp-sync-send-time", true);
+        }
+        if rtpbin.has_property("add-reference-timestamp-meta", None) {
+            rtpbin.set_property("add-reference-timestamp-meta", true);
+        }
+    }
+
+    for payloader in pipeline.iterate_elements().into_iter().flatten().filter(|element| {
+        element
+            .factory()
+            .is_some_and(|factory| factory.klass().contains("Payloader"))
+    }) {
+        if payloader.has_property("add-reference-timestamp-meta", None) {
+            payloader.set_property("add-reference-timestamp-meta", true);
+        }
+
+        // If the `rtphdrextntp64` RTP header extension isn't installed, fall back to the
+        // reference-timestamp-meta above and skip carrying it over RTP for this payloader.
+        if let Ok(ntp_64_extension) = gst::ElementFactory::make("rtphdrextntp64").build() {
+            ntp_64_extension.set_property("id", 1u32);
+            // `add-extension` is a void action signal (`GstRTPBasePayload::add-extension`), not a
+            // `gboolean` one: emit_by_name::<T> panics at runtime if T doesn't match the signal's
+            // actual return type, so this must bind `()`, not `bool`.
+            payloader.emit_by_name::<()>("add-extension", &[&ntp_64_extension]);
+        }
+
+        if let Some(src_pad) = payloader.static_pad("src") {
+            let element_name = payloader.name().to_string();
+            let timestamps = rtp_sync_timestamps.clone();
+
+            src_pad.add_probe(gst::PadProbeType::BUFFER, move |_pad, probe_info| {
+                if let Some(meta) = probe_info
+                    .buffer()
+                    .and_then(|buffer| buffer.meta::<gst::ReferenceTimestampMeta>())
+                {
+                    timestamps
+                        .lock()
+                        .unwrap()
+                        .insert(element_name.clone(), meta.timestamp());
+                }
+                gst::PadProbeReturn::Ok
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Sets `rtp_latency_ms`, if configured, uniformly on every rtpbin/jitterbuffer element's
+/// `latency` property. This is a plain property and takes effect whenever it's set, so unlike
+/// `pipeline_latency_ms` below it's applied at construction, before the pipeline ever reaches
+/// PLAYING.
+fn configure_startup_latency(pipeline: &gst::Pipeline, config: &cli::PipelineConfig) {
+    if let Some(rtp_latency_ms) = config.rtp_latency_ms {
+        for factory_name in ["rtpbin", "rtpjitterbuffer"] {
+            for element in pipeline
+                .iterate_all_by_element_factory_name(factory_name)
+                .into_iter()
+                .flatten()
+            {
+                if element.has_property("latency", None) {
+                    element.set_property("latency", rtp_latency_ms as u32);
+                }
+            }
+        }
+    }
+}
+
+/// Pushes `pipeline_latency_ms`, if configured, as a `gst::event::Latency` followed by
+/// `recalculate_latency`, so the whole pipeline settles to a fixed, known latency. Since every
+/// pipeline in the process already shares the same clock and base time, applying the same
+/// explicit latency to each of them is what lets independently parsed pipelines render
+/// synchronized output. Unlike `configure_startup_latency`, a latency event/recalculation has no
+/// effect in NULL, so this must run after the PLAYING transition, not at construction.
+fn apply_pipeline_latency(pipeline: &gst::Pipeline, config: &cli::PipelineConfig) {
+    if let Some(pipeline_latency_ms) = config.pipeline_latency_ms {
+        let latency_event = gst::event::Latency::new(gst::ClockTime::from_mseconds(pipeline_latency_ms));
+        let _ = pipeline.send_event(latency_event);
+        let _ = pipeline.recalculate_latency();
+    }
+}
+
+/// Spawns `future` (one of `run`'s command/bus tasks) onto `task_set`, routing it through
+/// `throttled_runtime` first when one is configured. Without this, only the outer per-pipeline
+/// future in `main.rs` would have its wakeups coalesced by the throttled runtime, while the
+/// command and bus tasks below it — the actual source of most wakeups — would keep running on
+/// the default per-wakeup scheduling regardless of `--throttled-runtime`.
+///
+/// `task_set` still owns every task either way, so `run`'s `join_next`/`abort_all` loop keeps
+/// working unchanged; when throttled, `task_set` holds a thin forwarding task that just awaits
+/// the real task's completion on the throttled runtime. Note that aborting that forwarding task
+/// (e.g. after a sibling task's fatal error) stops `run` from waiting on it, but cannot itself
+/// preempt the real task already running on the throttled runtime's own thread.
+fn spawn_task(
+    task_set: &mut tokio::task::JoinSet<Result<()>>,
+    throttled_runtime: &Option<Arc<crate::throttle::ThrottledRuntime>>,
+    future: impl Future<Output = Result<()>> + Send + 'static,
+) {
+    match throttled_runtime {
+        Some(rt) => {
+            let done_rx = rt.spawn(future);
+            task_set.spawn(async move { done_rx.await.unwrap_or(Ok(())) });
+        }
+        None => {
+            task_set.spawn(future);
+        }
+    }
 }
 
 impl Pipeline {
@@ -36,6 +187,10 @@ impl Pipeline {
         config: &cli::PipelineConfig,
         clock: Option<&gst::Clock>,
         basetime: &gst::ClockTime,
+        command_tx: broadcast::Sender<cli::SubCommand>,
+        response_tx: broadcast::Sender<String>,
+        rules: std::sync::Arc<Vec<crate::rules::Rule>>,
+        throttled_runtime: Option<std::sync::Arc<crate::throttle::ThrottledRuntime>>,
     ) -> Result<Arc<Self>> {
         let pipeline_spec = &config.spec.join(" ");
 
@@ -51,17 +206,33 @@ impl Pipeline {
         pipeline.set_start_time(gst::ClockTime::NONE);
         pipeline.set_base_time(basetime.to_owned());
 
+        let rtp_sync_timestamps = std::sync::Arc::new(std::sync::Mutex::new(
+            std::collections::HashMap::new(),
+        ));
+
+        if config.rapid_rtp_sync {
+            configure_rapid_rtp_sync(&pipeline, &rtp_sync_timestamps)?;
+        }
+
+        configure_startup_latency(&pipeline, config);
+
         Ok(Arc::new(Self {
             config: config.to_owned(),
             pipeline,
             shared_settings: std::sync::Arc::new(std::sync::Mutex::new(PipelineSharedSettings {
                 cli_stopped: false,
             })),
+            response_tx,
+            command_tx,
+            rules,
+            rtp_sync_timestamps,
+            throttled_runtime,
         }))
     }
 
     pub(crate) fn start(self: &Arc<Self>) -> Result<()> {
         self.pipeline.set_state(gst::State::Playing)?;
+        apply_pipeline_latency(&self.pipeline, &self.config);
         Ok(())
     }
 
@@ -76,11 +247,14 @@ impl Pipeline {
         let eos_event = gst::event::Eos::new();
         let _ = self.pipeline.send_event(eos_event);
 
-        // mark the pipeline as being stopped manually via CLI
-        {
-            let mut shared_settings = self.shared_settings.lock().unwrap();
-            shared_settings.cli_stopped = true;
-        }
+        self.mark_cli_stopped();
+    }
+
+    /// Marks the pipeline as stopped intentionally (via CLI command or a force-stop), so the
+    /// restart-policy supervisor in `main.rs` knows not to restart it.
+    fn mark_cli_stopped(self: &Arc<Self>) {
+        let mut shared_settings = self.shared_settings.lock().unwrap();
+        shared_settings.cli_stopped = true;
     }
 
     /// Handle a command
@@ -172,11 +346,47 @@ impl Pipeline {
 
                     if let gst::QueryView::Latency(latency) = query.view() {
                         let (is_live, min_latency, max_latency) = latency.result();
-                        println!("{}: Latency: is_live: {is_live}, min_latency: {min_latency}, max_latency: {max_latency:?}", self.config.name);
+                        let response = format!("{}: Latency: is_live: {is_live}, min_latency: {min_latency}, max_latency: {max_latency:?}", self.config.name);
+                        println!("{response}");
+                        let _ = self.response_tx.send(response);
                     }
                 }
                 Ok(false)
             }
+            cli::SubCommand::GetRtpSync(args) => {
+                if self.config.name == args.pipeline {
+                    let timestamps = self.rtp_sync_timestamps.lock().unwrap();
+
+                    let response = match args.element {
+                        Some(element_name) => match timestamps.get(&element_name) {
+                            Some(timestamp) => format!(
+                                "{}: RTP sync: {element_name}: reference-timestamp: {timestamp}",
+                                self.config.name
+                            ),
+                            None => format!(
+                                "{}: RTP sync: {element_name}: no reference timestamp observed yet",
+                                self.config.name
+                            ),
+                        },
+                        None if timestamps.is_empty() => format!(
+                            "{}: RTP sync: no reference timestamps observed yet",
+                            self.config.name
+                        ),
+                        None => {
+                            let mut entries: Vec<String> = timestamps
+                                .iter()
+                                .map(|(element_name, timestamp)| format!("{element_name}: {timestamp}"))
+                                .collect();
+                            entries.sort();
+                            format!("{}: RTP sync: {}", self.config.name, entries.join(", "))
+                        }
+                    };
+
+                    println!("{response}");
+                    let _ = self.response_tx.send(response);
+                }
+                Ok(false)
+            }
             _ => {
                 Ok(false)
             }
@@ -186,21 +396,28 @@ impl Pipeline {
 
     pub(crate) async fn run(
         self: &Arc<Self>,
-        mut _shutdown_receiver: broadcast::Receiver<()>,
+        shutdown_receiver: broadcast::Receiver<()>,
         mut command_receiver: broadcast::Receiver<cli::SubCommand>,
+        force_stop_receiver: broadcast::Receiver<()>,
     ) -> Result<()> {
         // first, change the pipeline state to PLAYING
         self.start()?;
 
         // holds references to the various tasks needed to handle
-        // the lifetime of the pipeline
-        let mut task_set = tokio::task::JoinSet::new();
+        // the lifetime of the pipeline. Each task reports Ok(()) on a clean stop, or the
+        // anyhow::Error that caused it to stop, which is what lets a fatal bus error propagate
+        // out of this function instead of being swallowed.
+        let mut task_set: tokio::task::JoinSet<Result<()>> = tokio::task::JoinSet::new();
 
         ///////////////////////////////////////////////////////
         // CLI command task
         let pipeline_command_clone = self.clone();
-        let mut shutdown_command_clone = _shutdown_receiver.resubscribe();
-        task_set.spawn(async move {
+        let mut shutdown_command_clone = shutdown_receiver.resubscribe();
+        let mut force_stop_command_clone = force_stop_receiver.resubscribe();
+        // Closed when the bus task below ends for any reason (e.g. EOS), so this task stops
+        // waiting for commands and `run` returns once the pipeline has finished on its own.
+        let (bus_done_tx, mut bus_done_rx) = tokio::sync::oneshot::channel::<()>();
+        spawn_task(&mut task_set, &self.throttled_runtime, async move {
             let pipeline = pipeline_command_clone;
 
             loop {
@@ -210,6 +427,19 @@ impl Pipeline {
                         pipeline.signal_stop();
                         break;
                     },
+                    _ = force_stop_command_clone.recv() => {
+                        // stop-timeout elapsed, or a second shutdown signal arrived: stop
+                        // immediately instead of waiting for EOS to drain
+                        println!("{}: forcing immediate stop", pipeline.config.name);
+                        let _ = pipeline.stop();
+                        // A force-stop is still an intentional stop, not a failure: mark it so
+                        // the restart-policy supervisor does not restart this pipeline.
+                        pipeline.mark_cli_stopped();
+                        break;
+                    },
+                    _ = &mut bus_done_rx => {
+                        break;
+                    },
                     Ok(command) = command_receiver.recv() => {
                         let should_break = pipeline.handle_command(command);
                         if let Ok(true) = should_break {
@@ -218,14 +448,19 @@ impl Pipeline {
                     }
                 }
             }
+
+            Ok(())
         });
 
         ///////////////////////////////////////////////////////
         // Bus message handling task
         let pipeline_bus_task_clone = self.clone();
-        task_set.spawn(async move {
-        
+        let mut shutdown_bus_clone = shutdown_receiver.resubscribe();
+        let mut force_stop_bus_clone = force_stop_receiver.resubscribe();
+        spawn_task(&mut task_set, &self.throttled_runtime, async move {
+
         let pipeline = pipeline_bus_task_clone;
+        let _bus_done_tx = bus_done_tx;
 
         // search for all intersrc elements and add a probe to the src pad to handle EOS events
         for child in pipeline
@@ -288,53 +523,127 @@ impl Pipeline {
             }
         }
 
-        if let Some(bus) = pipeline.pipeline.bus() {
-            for msg in bus.iter_timed(gst::ClockTime::NONE) {
-                match msg.view() {
-                    gst::MessageView::Eos(msg) => {
-                        println!(
-                            "{}: End-Of-Stream: {:?}",
-                            pipeline.config.name, msg
-                        );
-                        // stop the pipeline and break the loop
-                        let _ = pipeline.stop();
-                        break;
-                    }
-                    gst::MessageView::Error(err) => {
-                        println!(
-                            "{}: Error message: {:?}",
-                            pipeline.config.name,
-                            err.message()
-                        );
+        let Some(bus) = pipeline.pipeline.bus() else {
+            return Err(anyhow::anyhow!(
+                "{}: unable to get bus for pipeline",
+                pipeline.config.name
+            ));
+        };
+
+        // An async bus stream, polled alongside the shutdown/force-stop receivers below,
+        // mirrors gst-plugins-rs's own bus-watch examples: unlike `bus.iter_timed`, it never
+        // blocks the tokio worker and lets shutdown interrupt it immediately.
+        let mut messages = bus.stream();
+
+        loop {
+            tokio::select! {
+                msg = messages.next() => {
+                    let Some(msg) = msg else { break; };
+
+                    // Evaluate the bus-event rules against this message before acting on it
+                    // below. This runs on the pipeline's own bus task, never on the GLib main loop.
+                    if !pipeline.rules.is_empty() {
+                        let env = crate::rules::message_env(&pipeline.config.name, &msg);
+                        crate::rules::evaluate(&pipeline.rules, &env, &pipeline.command_tx);
                     }
-                    gst::MessageView::Latency(msg) => {
-                        println!(
-                            "{}: Latency message: {msg:?}",
-                            pipeline.config.name
-                        );
-
-                        let mut query = gst::query::Latency::new();
-                        let _ = pipeline.pipeline.query(query.query_mut());
-                        if let gst::QueryView::Latency(latency) = query.view() {
-                            let (is_live, min_latency, max_latency) = latency.result();
-                            println!("{}: Latency: is_live: {is_live}, min_latency: {min_latency}, max_latency: {max_latency:?}", pipeline.config.name);
-                        }
 
-                        let _ = pipeline.pipeline.recalculate_latency();
+                    match msg.view() {
+                        gst::MessageView::Eos(msg) => {
+                            println!(
+                                "{}: End-Of-Stream: {:?}",
+                                pipeline.config.name, msg
+                            );
+                            // stop the pipeline and break the loop
+                            let _ = pipeline.stop();
+                            break;
+                        }
+                        gst::MessageView::Error(err) => {
+                            println!(
+                                "{}: Error message: {:?}",
+                                pipeline.config.name,
+                                err.message()
+                            );
+                            // a fatal error: stop this pipeline and propagate it out of `run`,
+                            // tearing down this pipeline's own task_set (the CLI command task
+                            // above, this bus task) so it doesn't wedge half-stopped. This is
+                            // scoped to this one pipeline only — `supervise_pipeline` in main.rs
+                            // decides from its restart policy whether to restart just this
+                            // pipeline; sibling pipelines are unaffected and keep running.
+                            let _ = pipeline.stop();
+                            return Err(anyhow::anyhow!(
+                                "{}: fatal error from {}: {}",
+                                pipeline.config.name,
+                                err.src().map(|s| s.path_string()).unwrap_or_default(),
+                                err.error(),
+                            ));
+                        }
+                        gst::MessageView::Latency(msg) => {
+                            println!(
+                                "{}: Latency message: {msg:?}",
+                                pipeline.config.name
+                            );
+
+                            let mut query = gst::query::Latency::new();
+                            let _ = pipeline.pipeline.query(query.query_mut());
+                            if let gst::QueryView::Latency(latency) = query.view() {
+                                let (is_live, min_latency, max_latency) = latency.result();
+                                println!("{}: Latency: is_live: {is_live}, min_latency: {min_latency}, max_latency: {max_latency:?}", pipeline.config.name);
+                            }
+
+                            let _ = pipeline.pipeline.recalculate_latency();
+                        }
+                        _ => {}
                     }
-                    _ => {}
+                }
+                _ = shutdown_bus_clone.recv() => {
+                    // The CLI command task owns sending the EOS event, but this task must keep
+                    // polling the bus afterwards: breaking here would exit before the resulting
+                    // Eos message is ever observed, skipping the clean stop()->NULL below and
+                    // risking muxers/files being torn down mid-write. Draining continues until
+                    // either that Eos message arrives or force_stop fires (stop-timeout/second
+                    // signal), so both remain reachable.
+                    println!("{}: shutdown signal received, draining until EOS", pipeline.config.name);
+                }
+                _ = force_stop_bus_clone.recv() => {
+                    let _ = pipeline.stop();
+                    // Same as the CLI command task: a force-stop is intentional, not a failure.
+                    pipeline.mark_cli_stopped();
+                    break;
                 }
             }
-        } else {
-            println!(
-                "ERROR: unable to get bus for pipeline: {}",
-                pipeline.config.name
-            );
         }
-    });
 
-        // wait for all the tasks controlling the pipeline to finish
-        while task_set.join_next().await.is_some() {}
         Ok(())
+    });
+
+        // Wait for every task controlling this pipeline to finish. A fatal error from one task
+        // (e.g. the bus task above) aborts the rest of *this pipeline's* task_set, so they don't
+        // keep running a pipeline that has already failed, and the first real error is
+        // propagated out of `run` for `supervise_pipeline` to act on. This does not reach into
+        // other pipelines' task_sets — each pipeline's tasks, and its restart policy, are
+        // independent of its siblings.
+        let mut first_error = None;
+        while let Some(result) = task_set.join_next().await {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => {
+                    if first_error.is_none() {
+                        first_error = Some(err);
+                    }
+                    task_set.abort_all();
+                }
+                Err(join_err) if join_err.is_cancelled() => {}
+                Err(join_err) => {
+                    if first_error.is_none() {
+                        first_error = Some(join_err.into());
+                    }
+                }
+            }
+        }
+
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
     }
 }
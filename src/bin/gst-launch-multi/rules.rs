@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::cli;
+
+use anyhow::{anyhow, Result};
+use tokio::sync::broadcast;
+
+/// Minimum time between two firings of the same rule, guarding against an action whose effect
+/// causes the bus message that triggered it to be re-posted (e.g. a property change that
+/// re-emits the same element message), which would otherwise fire the rule in a tight loop.
+const MIN_REFIRE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A predicate evaluated against a bus-message environment (`type`, `source`, `pipeline`, and
+/// the extra fields `message_env` adds for state-changed and element messages).
+#[derive(Debug, Clone)]
+pub(crate) enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Eq(String, String),
+    Contains(String, String),
+}
+
+impl Expr {
+    fn eval(&self, env: &HashMap<String, String>) -> bool {
+        match self {
+            Expr::And(children) => children.iter().all(|child| child.eval(env)),
+            Expr::Or(children) => children.iter().any(|child| child.eval(env)),
+            Expr::Eq(field, value) => env.get(field).is_some_and(|v| v == value),
+            Expr::Contains(field, value) => env.get(field).is_some_and(|v| v.contains(value.as_str())),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Atom(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            '(' => {
+                tokens.push(Token::LParen);
+                chars.next();
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                chars.next();
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => value.push(c),
+                        None => return Err(anyhow!("unterminated string literal in match expression")),
+                    }
+                }
+                tokens.push(Token::Atom(value));
+            }
+            _ => {
+                let mut atom = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    atom.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Atom(atom));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_expr(tokens: &[Token], pos: &mut usize) -> Result<Expr> {
+    match tokens.get(*pos) {
+        Some(Token::LParen) => *pos += 1,
+        _ => return Err(anyhow!("expected '(' to start a match expression")),
+    }
+
+    let op = match tokens.get(*pos) {
+        Some(Token::Atom(op)) => op.clone(),
+        _ => return Err(anyhow!("expected an operator after '('")),
+    };
+    *pos += 1;
+
+    let expr = match op.as_str() {
+        "and" | "or" => {
+            let mut children = Vec::new();
+            while !matches!(tokens.get(*pos), Some(Token::RParen) | None) {
+                children.push(parse_expr(tokens, pos)?);
+            }
+            if op == "and" {
+                Expr::And(children)
+            } else {
+                Expr::Or(children)
+            }
+        }
+        "eq" | "contains" => {
+            let field = match tokens.get(*pos) {
+                Some(Token::Atom(field)) => field.clone(),
+                _ => return Err(anyhow!("expected a field name after '{op}'")),
+            };
+            *pos += 1;
+
+            let value = match tokens.get(*pos) {
+                Some(Token::Atom(value)) => value.clone(),
+                _ => return Err(anyhow!("expected a value after '{op} {field}'")),
+            };
+            *pos += 1;
+
+            if op == "eq" {
+                Expr::Eq(field, value)
+            } else {
+                Expr::Contains(field, value)
+            }
+        }
+        other => return Err(anyhow!("unknown match operator: {other}")),
+    };
+
+    match tokens.get(*pos) {
+        Some(Token::RParen) => *pos += 1,
+        _ => return Err(anyhow!("expected ')' to close '{op}' expression")),
+    }
+
+    Ok(expr)
+}
+
+/// Parses a match expression such as `(and (eq type "eos") (eq pipeline "camA"))` into an AST.
+pub(crate) fn parse(input: &str) -> Result<Expr> {
+    let tokens = tokenize(input)?;
+    let mut pos = 0;
+    let expr = parse_expr(&tokens, &mut pos)?;
+
+    if pos != tokens.len() {
+        return Err(anyhow!("unexpected trailing tokens in match expression"));
+    }
+
+    Ok(expr)
+}
+
+/// The action fired when a rule's match expression evaluates to true: either one of the
+/// existing `SubCommand`s, broadcast on the command channel, or a shell command to spawn.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(untagged)]
+pub(crate) enum RuleAction {
+    Command(cli::SubCommand),
+    Shell { shell: String },
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub(crate) struct RuleSpec {
+    pub(crate) r#match: String,
+    pub(crate) action: RuleAction,
+}
+
+/// A rule whose match expression has been parsed once at startup, paired with the action to
+/// fire and a debounce guard against re-triggering itself in a tight loop.
+pub(crate) struct Rule {
+    expr: Expr,
+    action: RuleAction,
+    last_fired: Mutex<Option<tokio::time::Instant>>,
+}
+
+/// Loads bus-event rules from a YAML or JSON file, parsing each rule's match expression once.
+pub(crate) fn load_rules_from_file(path: &str) -> Result<Vec<Rule>> {
+    let contents = std::fs::read_to_string(path)?;
+
+    let specs: Vec<RuleSpec> = if path.ends_with(".json") {
+        serde_json::from_str(&contents)?
+    } else {
+        serde_yaml::from_str(&contents)?
+    };
+
+    specs
+        .into_iter()
+        .map(|spec| {
+            Ok(Rule {
+                expr: parse(&spec.r#match)?,
+                action: spec.action,
+                last_fired: Mutex::new(None),
+            })
+        })
+        .collect()
+}
+
+/// Builds the environment a bus message is evaluated against: `pipeline`, `type`, `source`,
+/// `old-state`/`new-state` for state-changed messages, and, for element messages, the
+/// structure's own fields.
+pub(crate) fn message_env(pipeline_name: &str, msg: &gst::message::Message) -> HashMap<String, String> {
+    let mut env = HashMap::new();
+    env.insert("pipeline".to_string(), pipeline_name.to_string());
+
+    let type_name = match msg.view() {
+        gst::MessageView::Eos(_) => "eos",
+        gst::MessageView::Error(_) => "error",
+        gst::MessageView::Element(_) => "element",
+        gst::MessageView::StateChanged(_) => "state-changed",
+        gst::MessageView::Latency(_) => "latency",
+        _ => "other",
+    };
+    env.insert("type".to_string(), type_name.to_string());
+
+    if let Some(src) = msg.src() {
+        env.insert("source".to_string(), src.name().to_string());
+    }
+
+    if let gst::MessageView::StateChanged(state_changed) = msg.view() {
+        env.insert("old-state".to_string(), format!("{:?}", state_changed.old()));
+        env.insert("new-state".to_string(), format!("{:?}", state_changed.current()));
+    }
+
+    if let gst::MessageView::Element(element_msg) = msg.view() {
+        if let Some(structure) = element_msg.structure() {
+            for (field, value) in structure.iter() {
+                env.insert(field.to_string(), format!("{value:?}"));
+            }
+        }
+    }
+
+    env
+}
+
+/// Evaluates every rule against `env`, top to bottom, firing the action of each one whose
+/// match expression is true. Runs synchronously on the caller's bus-handling task, so it must
+/// never block on I/O beyond spawning a shell command.
+pub(crate) fn evaluate(
+    rules: &[Rule],
+    env: &HashMap<String, String>,
+    command_tx: &broadcast::Sender<cli::SubCommand>,
+) {
+    for rule in rules {
+        if !rule.expr.eval(env) {
+            continue;
+        }
+
+        {
+            let mut last_fired = rule.last_fired.lock().unwrap();
+            let now = tokio::time::Instant::now();
+            if let Some(last) = *last_fired {
+                if now.duration_since(last) < MIN_REFIRE_INTERVAL {
+                    continue;
+                }
+            }
+            *last_fired = Some(now);
+        }
+
+        match &rule.action {
+            RuleAction::Command(command) => {
+                let _ = command_tx.send(command.clone());
+            }
+            RuleAction::Shell { shell } => {
+                match std::process::Command::new("sh").arg("-c").arg(shell).spawn() {
+                    Ok(mut child) => {
+                        // `spawn` only starts the process; rules can fire repeatedly, so this
+                        // must be reaped or every firing leaks a zombie until the process exits.
+                        let shell = shell.clone();
+                        std::thread::spawn(move || match child.wait() {
+                            Ok(status) if !status.success() => {
+                                println!("Rules: shell action {shell:?} exited with {status}");
+                            }
+                            Ok(_) => {}
+                            Err(e) => {
+                                println!("Rules: failed to wait on shell action {shell:?}: {e}");
+                            }
+                        });
+                    }
+                    Err(e) => println!("Rules: failed to spawn shell action {shell:?}: {e}"),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Staggers pipeline admission (the NULL->PLAYING transition) across fixed-size time quanta,
+/// instead of a single fixed startup delay between every pipeline. Each call to `admit`
+/// reserves the next quantum boundary, so dozens of pipelines starting together are spread
+/// out over time rather than all landing on the same instant.
+#[derive(Clone)]
+pub(crate) struct Throttle {
+    quantum: Duration,
+    epoch: Instant,
+    next_slot: Arc<AtomicU64>,
+}
+
+impl Throttle {
+    pub(crate) fn new(quantum_ms: u64) -> Self {
+        Self {
+            quantum: Duration::from_millis(quantum_ms.max(1)),
+            epoch: Instant::now(),
+            next_slot: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Reserves and waits for the next staggered admission slot.
+    pub(crate) async fn admit(&self) {
+        // Never reserve a slot that has already passed, so admissions requested long after
+        // startup are still staggered relative to each other.
+        let now_slot = (self.epoch.elapsed().as_nanos() / self.quantum.as_nanos()) as u64;
+        let slot = self
+            .next_slot
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |next| {
+                Some(next.max(now_slot) + 1)
+            })
+            .unwrap_or_else(|next| next)
+            .max(now_slot);
+        let deadline = self.epoch + self.quantum * u32::try_from(slot + 1).unwrap_or(u32::MAX);
+        tokio::time::sleep_until(deadline).await;
+    }
+}
+
+type PipelineFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Pushes a task's id back onto the shared ready queue when it is woken, instead of letting a
+/// generic executor repoll it immediately. This is what lets [`ThrottledRuntime`] batch the
+/// wakeups that arrive during one quantum into a single poll pass.
+struct TaskWaker {
+    id: u64,
+    ready: Arc<Mutex<VecDeque<u64>>>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let mut ready = self.ready.lock().unwrap();
+        if !ready.contains(&self.id) {
+            ready.push_back(self.id);
+        }
+    }
+}
+
+/// Runs spawned futures on a dedicated background thread, imported from the threadshare
+/// "throttling" scheduling strategy: rather than repolling a task the instant it wakes, wakeups
+/// are coalesced onto a ready queue and the whole batch is drained in a single poll pass once
+/// per quantum, then the thread sleeps until the next quantum boundary. The tradeoff is up to
+/// one quantum of added latency in exchange for far fewer context switches, which matters once a
+/// process is running dozens of pipelines, each with their own command and bus tasks. Opt-in via
+/// `--throttled-runtime`; the default remains one wakeup per event, handled by the main runtime.
+pub(crate) struct ThrottledRuntime {
+    spawn_tx: std::sync::mpsc::Sender<(u64, PipelineFuture)>,
+    next_id: AtomicU64,
+}
+
+impl ThrottledRuntime {
+    /// Spawns the background thread that drives the throttled poll loop, coalescing wakeups
+    /// into quanta of `quantum`.
+    pub(crate) fn spawn_thread(quantum: Duration) -> Arc<Self> {
+        let (spawn_tx, spawn_rx) = std::sync::mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("pipeline-throttle".to_string())
+            .spawn(move || Self::run(quantum, spawn_rx))
+            .expect("failed to spawn throttled runtime thread");
+
+        Arc::new(Self {
+            spawn_tx,
+            next_id: AtomicU64::new(0),
+        })
+    }
+
+    /// Schedules `future` to run on the throttled runtime. The returned receiver resolves to
+    /// `future`'s output once it completes, so callers can still wait for it to finish (and get
+    /// its result) the same way they would a `JoinHandle`.
+    pub(crate) fn spawn<T: Send + 'static>(
+        &self,
+        future: impl Future<Output = T> + Send + 'static,
+    ) -> tokio::sync::oneshot::Receiver<T> {
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+        let future: PipelineFuture = Box::pin(async move {
+            let result = future.await;
+            let _ = done_tx.send(result);
+        });
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        // The background thread may have exited if this process is shutting down; the future
+        // is then dropped along with `done_tx`, so `done_rx` resolves with an error and the
+        // caller just stops waiting on it.
+        let _ = self.spawn_tx.send((id, future));
+
+        done_rx
+    }
+
+    fn run(quantum: Duration, spawn_rx: std::sync::mpsc::Receiver<(u64, PipelineFuture)>) {
+        // The futures we drive still need a tokio reactor and timer (channel recv, sleep, ...),
+        // so the batching loop below runs on top of its own single-threaded tokio runtime.
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to build throttled runtime");
+
+        rt.block_on(async move {
+            let mut tasks: HashMap<u64, PipelineFuture> = HashMap::new();
+            let ready: Arc<Mutex<VecDeque<u64>>> = Arc::new(Mutex::new(VecDeque::new()));
+            let mut next_wake = Instant::now() + quantum;
+
+            loop {
+                // Pick up newly spawned tasks; poll each of them at least once so a task that
+                // completes without ever registering a waker still makes progress right away.
+                while let Ok((id, future)) = spawn_rx.try_recv() {
+                    tasks.insert(id, future);
+                    ready.lock().unwrap().push_back(id);
+                }
+
+                // Drain every task woken during this quantum in a single pass.
+                let batch: Vec<u64> = ready.lock().unwrap().drain(..).collect();
+                for id in batch {
+                    let Some(future) = tasks.get_mut(&id) else {
+                        continue;
+                    };
+
+                    let waker = Waker::from(Arc::new(TaskWaker {
+                        id,
+                        ready: ready.clone(),
+                    }));
+                    let mut cx = Context::from_waker(&waker);
+
+                    if future.as_mut().poll(&mut cx).is_ready() {
+                        tasks.remove(&id);
+                    }
+                }
+
+                tokio::time::sleep_until(next_wake).await;
+                next_wake += quantum;
+            }
+        });
+    }
+}